@@ -0,0 +1,69 @@
+use lazy_static::lazy_static;
+use spin::Mutex;
+
+use crate::keyboard::{self, Key, ScancodeDecoder};
+use crate::vga_buffer::WRITER;
+
+/// Maximum number of bytes a single line of input can hold.
+const LINE_CAPACITY: usize = 128;
+
+lazy_static! {
+  /// Global interactive console, pairing the scancode decoder with a line buffer.
+  pub static ref CONSOLE: Mutex<Console> = Mutex::new(Console::new());
+}
+
+/// A line-oriented reader over the PS/2 keyboard that echoes through `WRITER`.
+pub struct Console {
+  decoder: ScancodeDecoder,
+  buffer: [u8; LINE_CAPACITY],
+  length: usize,
+}
+
+impl Console {
+  const fn new() -> Console {
+    Console {
+      decoder: ScancodeDecoder::new(),
+      buffer: [0; LINE_CAPACITY],
+      length: 0,
+    }
+  }
+
+  /// Reads a single line, echoing printable characters and erasing cells on
+  /// Backspace, and returns it (without the newline) once Enter is pressed.
+  pub fn read_line(&mut self) -> &str {
+    self.length = 0;
+
+    loop {
+      let scancode = keyboard::read_scancode();
+      match self.decoder.decode(scancode) {
+        Some(Key::Char(byte)) => {
+          if self.length < LINE_CAPACITY {
+            self.buffer[self.length] = byte;
+            self.length += 1;
+            WRITER.lock().write_byte(byte);
+          }
+        }
+        Some(Key::Backspace) => {
+          if self.length > 0 {
+            self.length -= 1;
+            WRITER.lock().backspace();
+          }
+        }
+        Some(Key::Enter) => {
+          WRITER.lock().write_byte(b'\n');
+          break;
+        }
+        None => {}
+      }
+    }
+
+    // The line buffer only ever holds ASCII from the keymap, so this is valid.
+    core::str::from_utf8(&self.buffer[..self.length]).unwrap_or("")
+  }
+
+  /// Prints `prompt`, then reads and returns a line of input.
+  pub fn prompt(&mut self, prompt: &str) -> &str {
+    WRITER.lock().write_string(prompt);
+    self.read_line()
+  }
+}
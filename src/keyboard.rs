@@ -0,0 +1,185 @@
+//! PS/2 set-1 scancode decoding. The decoder is deliberately independent of any
+//! input source so that a serial-input path can later feed the same line editor.
+
+/// A decoded key event from the scancode stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+  /// A printable character.
+  Char(u8),
+  /// The Enter/Return key.
+  Enter,
+  /// The Backspace key.
+  Backspace,
+}
+
+/// Translates PS/2 set-1 make/break codes into `Key` events, tracking the
+/// Shift and CapsLock modifier state across calls.
+pub struct ScancodeDecoder {
+  shift: bool,
+  caps_lock: bool,
+}
+
+/// Lookup table indexed by make-code, giving the unshifted and shifted ASCII
+/// character for each key. A `0` entry means the key produces no character.
+static KEYMAP: [(u8, u8); 0x40] = {
+  let mut map = [(0u8, 0u8); 0x40];
+  map[0x02] = (b'1', b'!');
+  map[0x03] = (b'2', b'@');
+  map[0x04] = (b'3', b'#');
+  map[0x05] = (b'4', b'$');
+  map[0x06] = (b'5', b'%');
+  map[0x07] = (b'6', b'^');
+  map[0x08] = (b'7', b'&');
+  map[0x09] = (b'8', b'*');
+  map[0x0A] = (b'9', b'(');
+  map[0x0B] = (b'0', b')');
+  map[0x0C] = (b'-', b'_');
+  map[0x0D] = (b'=', b'+');
+  map[0x10] = (b'q', b'Q');
+  map[0x11] = (b'w', b'W');
+  map[0x12] = (b'e', b'E');
+  map[0x13] = (b'r', b'R');
+  map[0x14] = (b't', b'T');
+  map[0x15] = (b'y', b'Y');
+  map[0x16] = (b'u', b'U');
+  map[0x17] = (b'i', b'I');
+  map[0x18] = (b'o', b'O');
+  map[0x19] = (b'p', b'P');
+  map[0x1A] = (b'[', b'{');
+  map[0x1B] = (b']', b'}');
+  map[0x1E] = (b'a', b'A');
+  map[0x1F] = (b's', b'S');
+  map[0x20] = (b'd', b'D');
+  map[0x21] = (b'f', b'F');
+  map[0x22] = (b'g', b'G');
+  map[0x23] = (b'h', b'H');
+  map[0x24] = (b'j', b'J');
+  map[0x25] = (b'k', b'K');
+  map[0x26] = (b'l', b'L');
+  map[0x27] = (b';', b':');
+  map[0x28] = (b'\'', b'"');
+  map[0x29] = (b'`', b'~');
+  map[0x2B] = (b'\\', b'|');
+  map[0x2C] = (b'z', b'Z');
+  map[0x2D] = (b'x', b'X');
+  map[0x2E] = (b'c', b'C');
+  map[0x2F] = (b'v', b'V');
+  map[0x30] = (b'b', b'B');
+  map[0x31] = (b'n', b'N');
+  map[0x32] = (b'm', b'M');
+  map[0x33] = (b',', b'<');
+  map[0x34] = (b'.', b'>');
+  map[0x35] = (b'/', b'?');
+  map[0x39] = (b' ', b' ');
+  map
+};
+
+const SCANCODE_LSHIFT: u8 = 0x2A;
+const SCANCODE_RSHIFT: u8 = 0x36;
+const SCANCODE_CAPSLOCK: u8 = 0x3A;
+const SCANCODE_ENTER: u8 = 0x1C;
+const SCANCODE_BACKSPACE: u8 = 0x0E;
+
+impl ScancodeDecoder {
+  pub const fn new() -> ScancodeDecoder {
+    ScancodeDecoder {
+      shift: false,
+      caps_lock: false,
+    }
+  }
+
+  /// Feeds a single raw scancode and returns the `Key` it produces, if any.
+  /// Modifier presses/releases and unmapped keys return `None`.
+  pub fn decode(&mut self, scancode: u8) -> Option<Key> {
+    // Break codes carry the high bit; only Shift releases are interesting.
+    if scancode & 0x80 != 0 {
+      if let SCANCODE_LSHIFT | SCANCODE_RSHIFT = scancode & 0x7f {
+        self.shift = false;
+      }
+      return None;
+    }
+
+    match scancode {
+      SCANCODE_LSHIFT | SCANCODE_RSHIFT => {
+        self.shift = true;
+        None
+      }
+      SCANCODE_CAPSLOCK => {
+        self.caps_lock = !self.caps_lock;
+        None
+      }
+      SCANCODE_ENTER => Some(Key::Enter),
+      SCANCODE_BACKSPACE => Some(Key::Backspace),
+      _ => {
+        let (base, shifted) = match KEYMAP.get(scancode as usize) {
+          Some(&entry) => entry,
+          None => return None,
+        };
+        if base == 0 {
+          return None;
+        }
+        // CapsLock only flips the case of alphabetic keys.
+        let uppercase = if base.is_ascii_alphabetic() {
+          self.shift ^ self.caps_lock
+        } else {
+          self.shift
+        };
+        Some(Key::Char(if uppercase { shifted } else { base }))
+      }
+    }
+  }
+}
+
+/// Busy-waits for the next scancode from the PS/2 controller's data port.
+#[cfg(not(test))]
+pub fn read_scancode() -> u8 {
+  use crate::port::inb;
+  unsafe {
+    // Wait until the controller's output buffer is full.
+    while inb(0x64) & 1 == 0 {}
+    inb(0x60)
+  }
+}
+
+// Hosted test builds have no PS/2 controller; the console path that uses this is
+// never exercised there.
+#[cfg(test)]
+pub fn read_scancode() -> u8 {
+  0
+}
+
+#[cfg(test)]
+mod test {
+  use super::*;
+
+  #[test]
+  fn decodes_lowercase_letter() {
+    let mut decoder = ScancodeDecoder::new();
+    assert_eq!(decoder.decode(0x1E), Some(Key::Char(b'a')));
+  }
+
+  #[test]
+  fn shift_produces_uppercase_and_symbols() {
+    let mut decoder = ScancodeDecoder::new();
+    decoder.decode(0x2A); // press left shift
+    assert_eq!(decoder.decode(0x1E), Some(Key::Char(b'A')));
+    assert_eq!(decoder.decode(0x02), Some(Key::Char(b'!')));
+    decoder.decode(0xAA); // release left shift
+    assert_eq!(decoder.decode(0x1E), Some(Key::Char(b'a')));
+  }
+
+  #[test]
+  fn caps_lock_only_affects_letters() {
+    let mut decoder = ScancodeDecoder::new();
+    decoder.decode(0x3A); // toggle caps lock on
+    assert_eq!(decoder.decode(0x1E), Some(Key::Char(b'A')));
+    assert_eq!(decoder.decode(0x02), Some(Key::Char(b'1')));
+  }
+
+  #[test]
+  fn enter_and_backspace() {
+    let mut decoder = ScancodeDecoder::new();
+    assert_eq!(decoder.decode(0x1C), Some(Key::Enter));
+    assert_eq!(decoder.decode(0x0E), Some(Key::Backspace));
+  }
+}
@@ -0,0 +1,40 @@
+use log::{Level, LevelFilter, Metadata, Record, SetLoggerError};
+
+use crate::vga_buffer::{self, Color};
+
+/// The global kernel logger. Every record is colored by level and forwarded
+/// through the VGA `WRITER` (and, transitively, the serial mirror).
+struct KernelLogger;
+
+impl log::Log for KernelLogger {
+  fn enabled(&self, _metadata: &Metadata) -> bool {
+    true
+  }
+
+  fn log(&self, record: &Record) {
+    let color = match record.level() {
+      Level::Error => Color::Red,
+      Level::Warn => Color::Brown,
+      Level::Info => Color::Green,
+      Level::Debug => Color::Cyan,
+      Level::Trace => Color::DarkGray,
+    };
+
+    vga_buffer::_log(
+      color,
+      format_args!("[{}] {}: {}\n", record.level(), record.target(), record.args()),
+    );
+  }
+
+  fn flush(&self) {}
+}
+
+static LOGGER: KernelLogger = KernelLogger;
+
+/// Installs `KernelLogger` as the global `log` backend and lets every level
+/// through. Call once early during boot.
+pub fn init() -> Result<(), SetLoggerError> {
+  log::set_logger(&LOGGER)?;
+  log::set_max_level(LevelFilter::Trace);
+  Ok(())
+}
@@ -5,8 +5,15 @@
 
 use core::panic::PanicInfo;
 
+mod console;
+mod keyboard;
+mod logger;
+mod port;
+mod serial;
 mod vga_buffer;
 
+use log::info;
+
 use crate::vga_buffer::DebugLevel;
 
 // This is the function that is called during a panic
@@ -20,12 +27,14 @@ fn panic(info: &PanicInfo) -> ! {
 #[cfg(not(test))]
 #[no_mangle]
 pub extern "C" fn _start() -> ! {
+  logger::init().unwrap();
+
   debug!(DebugLevel::Core, "Hello {}\nGoodbye {}", 23, 24);
   debug!(DebugLevel::Process, "Hello {}\nGoodbye {}", 25, 26);
   println!("Other messages");
 
   clear_screen!();
 
-  debug!(DebugLevel::Core, "Starting boot...");
+  info!(target: DebugLevel::Core.target(), "Starting boot...");
   loop {}
 }
@@ -0,0 +1,23 @@
+use core::arch::asm;
+
+/// Writes a single byte to the given x86 I/O port.
+pub unsafe fn outb(port: u16, value: u8) {
+  asm!(
+    "out dx, al",
+    in("dx") port,
+    in("al") value,
+    options(nomem, nostack, preserves_flags),
+  );
+}
+
+/// Reads a single byte from the given x86 I/O port.
+pub unsafe fn inb(port: u16) -> u8 {
+  let value: u8;
+  asm!(
+    "in al, dx",
+    out("al") value,
+    in("dx") port,
+    options(nomem, nostack, preserves_flags),
+  );
+  value
+}
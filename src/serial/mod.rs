@@ -0,0 +1,108 @@
+use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+use lazy_static::lazy_static;
+use spin::Mutex;
+
+use crate::port::{inb, outb};
+
+/// Base I/O port of the first serial controller (COM1).
+const COM1: u16 = 0x3F8;
+
+/// Runtime switch controlling whether console output is mirrored to the serial
+/// port. Mirroring is on by default so a headless QEMU `-serial stdio` session
+/// sees everything the VGA buffer does; it can be turned off when the serial
+/// traffic is unwanted.
+static SERIAL_MIRROR: AtomicBool = AtomicBool::new(true);
+
+/// Enables or disables mirroring of console output to the serial port.
+pub fn set_mirror_enabled(enabled: bool) {
+  SERIAL_MIRROR.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns whether console output is currently mirrored to the serial port.
+pub fn mirror_enabled() -> bool {
+  SERIAL_MIRROR.load(Ordering::Relaxed)
+}
+
+lazy_static! {
+  /// Global handle to COM1, initialised on first use just like `WRITER`.
+  pub static ref SERIAL1: Mutex<SerialPort> = {
+    let mut port = SerialPort::new(COM1);
+    port.init();
+    Mutex::new(port)
+  };
+}
+
+/// A 16550-compatible UART addressed through its base I/O port.
+pub struct SerialPort {
+  base: u16,
+}
+
+impl SerialPort {
+  const fn new(base: u16) -> SerialPort {
+    SerialPort { base }
+  }
+
+  /// Brings the UART up in 38400 baud, 8N1 with the FIFO enabled.
+  pub fn init(&mut self) {
+    unsafe {
+      outb(self.base + 1, 0x00); // disable all interrupts
+      outb(self.base + 3, 0x80); // enable DLAB to program the divisor
+      outb(self.base, 0x03); // divisor low byte: 115200 / 38400 == 3
+      outb(self.base + 1, 0x00); // divisor high byte
+      outb(self.base + 3, 0x03); // 8 bits, no parity, one stop bit
+      outb(self.base + 2, 0xC7); // enable FIFO, clear it, 14-byte threshold
+      outb(self.base + 4, 0x0B); // IRQs enabled, RTS/DTR set
+    }
+  }
+
+  fn is_transmit_empty(&self) -> bool {
+    unsafe { inb(self.base + 5) & 0x20 != 0 }
+  }
+
+  /// Blocks until the transmit holding register is empty, then sends `byte`.
+  pub fn write_byte(&mut self, byte: u8) {
+    while !self.is_transmit_empty() {}
+    unsafe {
+      outb(self.base, byte);
+    }
+  }
+
+  /// Sends a string, translating `\n` into the CRLF pairs a terminal expects.
+  pub fn write_str(&mut self, s: &str) {
+    for byte in s.bytes() {
+      match byte {
+        b'\n' => {
+          self.write_byte(b'\r');
+          self.write_byte(b'\n');
+        }
+        byte => self.write_byte(byte),
+      }
+    }
+  }
+}
+
+impl fmt::Write for SerialPort {
+  fn write_str(&mut self, s: &str) -> fmt::Result {
+    SerialPort::write_str(self, s);
+    Ok(())
+  }
+}
+
+#[macro_export]
+macro_rules! serial_print {
+  ($($arg:tt)*) => ($crate::serial::_print(format_args!($($arg)*)));
+}
+
+#[macro_export]
+macro_rules! serial_println {
+  () => ($crate::serial_print!("\n"));
+  ($($arg:tt)*) => ($crate::serial_print!("{}\n", format_args!($($arg)*)));
+}
+
+/// Prints the given formatted string out the serial port through `SERIAL1`.
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+  use core::fmt::Write;
+  SERIAL1.lock().write_fmt(args).unwrap();
+}
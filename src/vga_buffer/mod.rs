@@ -3,11 +3,22 @@ use lazy_static::lazy_static;
 use spin::Mutex;
 use volatile::Volatile;
 
+#[cfg(not(test))]
+use crate::port::{inb, outb};
+
+/// CRTC address register: selects which internal CRTC register is accessed.
+const CRTC_ADDRESS: u16 = 0x3D4;
+/// CRTC data register: reads/writes the register selected through `CRTC_ADDRESS`.
+const CRTC_DATA: u16 = 0x3D5;
+
 lazy_static! {
   pub static ref WRITER: Mutex<Writer> = Mutex::new(Writer {
     column_position: 0,
     row_position: 0,
     color_code: ColorCode::new(Color::Yellow, Color::Black),
+    parser_state: ParserState::Ground,
+    params: [0; 8],
+    param_count: 0,
     buffer: unsafe { &mut *(0xb8000 as *mut Buffer) },
   });
 }
@@ -34,6 +45,43 @@ pub enum Color {
   White = 15,
 }
 
+impl Color {
+  /// Maps the numeric index of a `Color` back to the enum variant. This is the
+  /// inverse of the `repr(u8)` discriminant and is used by the SGR parser to
+  /// translate ANSI color codes into palette entries.
+  fn from_index(index: u8) -> Color {
+    match index {
+      0 => Color::Black,
+      1 => Color::Blue,
+      2 => Color::Green,
+      3 => Color::Cyan,
+      4 => Color::Red,
+      5 => Color::Magenta,
+      6 => Color::Brown,
+      7 => Color::LightGray,
+      8 => Color::DarkGray,
+      9 => Color::LightBlue,
+      10 => Color::LightGreen,
+      11 => Color::LightCyan,
+      12 => Color::LightRed,
+      13 => Color::Pink,
+      14 => Color::Yellow,
+      _ => Color::White,
+    }
+  }
+}
+
+/// State of the VTE-style escape-sequence parser embedded in the `Writer`. The
+/// state is retained between calls so sequences split across several
+/// `write_string`/`write_byte` invocations are still decoded correctly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParserState {
+  Ground,
+  Escape,
+  CsiEntry,
+  CsiParam,
+}
+
 #[allow(dead_code)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum DebugLevel {
@@ -41,6 +89,17 @@ pub enum DebugLevel {
   Process = 1,
 }
 
+impl DebugLevel {
+  /// The `log` target string this debug level maps to during the migration to
+  /// the `log` facade.
+  pub fn target(&self) -> &'static str {
+    match self {
+      DebugLevel::Core => "core",
+      DebugLevel::Process => "process",
+    }
+  }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct ColorCode(u8);
 
@@ -68,6 +127,9 @@ pub struct Writer {
   row_position: usize,
   column_position: usize,
   color_code: ColorCode,
+  parser_state: ParserState,
+  params: [u16; 8],
+  param_count: usize,
   buffer: &'static mut Buffer,
 }
 
@@ -81,44 +143,196 @@ impl Writer {
 
   pub fn write_string(&mut self, s: &str) {
     for byte in s.bytes() {
-      match byte {
-        // Only allow writing actual ascii characters
-        0x20...0x7e | b'\n' => self.write_byte(byte),
-        // Otherwise write a specific character
-        _ => self.write_byte(0xfe)
-      }
+      self.write_byte(byte);
     }
   }
 
+  /// Feeds a single byte through the escape-sequence state machine. Printable
+  /// bytes (and `\n`) reached in the `Ground` state are rendered immediately;
+  /// `ESC [ ... m` sequences are decoded and applied to `self.color_code`.
+  /// Malformed or unterminated sequences never panic and simply drop back to
+  /// `Ground`.
   pub fn write_byte(&mut self, byte: u8) {
-    match byte {
-      b'\n' => self.new_line(),
-      byte => {
-        if self.column_position >= BUFFER_WIDTH {
-          self.new_line();
+    match self.parser_state {
+      ParserState::Ground => match byte {
+        0x1b => self.parser_state = ParserState::Escape,
+        b'\n' => self.new_line(),
+        // Only render actual ascii characters
+        0x20...0x7e => self.put_byte(byte),
+        // Otherwise render a specific placeholder character
+        _ => self.put_byte(0xfe),
+      },
+      ParserState::Escape => match byte {
+        b'[' => {
+          self.params = [0; 8];
+          self.param_count = 0;
+          self.parser_state = ParserState::CsiEntry;
+        }
+        // Not a sequence we understand: abandon it and render the byte plainly.
+        _ => {
+          self.parser_state = ParserState::Ground;
+          self.write_byte(byte);
+        }
+      },
+      ParserState::CsiEntry | ParserState::CsiParam => match byte {
+        b'0'...b'9' => {
+          let slot = &mut self.params[self.param_count];
+          *slot = slot.saturating_mul(10).saturating_add((byte - b'0') as u16);
+          self.parser_state = ParserState::CsiParam;
+        }
+        b';' => {
+          if self.param_count < self.params.len() - 1 {
+            self.param_count += 1;
+          }
+          self.parser_state = ParserState::CsiParam;
+        }
+        // SGR: select graphic rendition.
+        b'm' => {
+          self.apply_sgr();
+          self.parser_state = ParserState::Ground;
         }
+        // Any other final byte is a sequence we don't implement; ignore it.
+        _ => self.parser_state = ParserState::Ground,
+      },
+    }
+  }
+
+  /// Writes a single already-decoded character cell to the buffer at the
+  /// current cursor position, wrapping to a new line when the row is full.
+  fn put_byte(&mut self, byte: u8) {
+    if self.column_position >= BUFFER_WIDTH {
+      self.new_line();
+    }
+
+    let row = self.row_position;
+    let col = self.column_position;
 
-        let row = self.row_position;
-        let col = self.column_position;
+    let color_code = self.color_code;
+    self.buffer.chars[row][col].write(ScreenChar {
+      ascii_character: byte,
+      color_code,
+    });
+    self.column_position += 1;
+    self.update_cursor();
+  }
 
-        let color_code = self.color_code;
-        self.buffer.chars[row][col].write(ScreenChar {
-          ascii_character: byte,
-          color_code,
-        });
-        self.column_position += 1;
+  /// Applies the accumulated SGR parameters to the current color. Numeric codes
+  /// are mapped onto the `Color` palette by `Color::from_index` and combined
+  /// into the attribute byte by `ColorCode::new`.
+  fn apply_sgr(&mut self) {
+    for i in 0..=self.param_count {
+      match self.params[i] {
+        0 => self.color_code = ColorCode::new(Color::Yellow, Color::Black),
+        code @ 30...37 => self.set_foreground(Color::from_index((code - 30) as u8)),
+        code @ 90...97 => self.set_foreground(Color::from_index((code - 90 + 8) as u8)),
+        code @ 40...47 => self.set_background(Color::from_index((code - 40) as u8)),
+        code @ 100...107 => self.set_background(Color::from_index((code - 100 + 8) as u8)),
+        // Unrecognized codes are ignored.
+        _ => {}
       }
     }
   }
 
+  fn set_foreground(&mut self, foreground: Color) {
+    let background = Color::from_index(self.color_code.0 >> 4);
+    self.color_code = ColorCode::new(foreground, background);
+  }
+
+  fn set_background(&mut self, background: Color) {
+    let foreground = Color::from_index(self.color_code.0 & 0x0f);
+    self.color_code = ColorCode::new(foreground, background);
+  }
+
   pub fn clear_screen(&mut self) {
     for row in 0..BUFFER_HEIGHT {
       self.clear_row(row);
     }
     self.row_position = 0;
     self.column_position = 0;
+    self.update_cursor();
+  }
+
+  /// Erases the character immediately to the left of the cursor and moves the
+  /// cursor back onto it, used to implement interactive Backspace.
+  pub fn backspace(&mut self) {
+    if self.column_position > 0 {
+      self.column_position -= 1;
+
+      let row = self.row_position;
+      let col = self.column_position;
+
+      let color_code = self.color_code;
+      self.buffer.chars[row][col].write(ScreenChar {
+        ascii_character: b' ',
+        color_code,
+      });
+      self.update_cursor();
+    }
+  }
+
+  /// Enables the blinking hardware cursor, giving its cell the scanline range
+  /// `start_scanline..=end_scanline`.
+  pub fn enable_cursor(&self, start_scanline: u8, end_scanline: u8) {
+    Writer::set_cursor_shape(start_scanline, end_scanline);
+  }
+
+  /// Hides the blinking hardware cursor.
+  pub fn disable_cursor(&self) {
+    Writer::hide_cursor();
+  }
+
+  /// Moves the software (and, in turn, hardware) cursor to an absolute cell.
+  pub fn set_position(&mut self, row: usize, col: usize) {
+    self.row_position = row;
+    self.column_position = col;
+    self.update_cursor();
+  }
+
+  /// Points the VGA hardware cursor at the current software position.
+  fn update_cursor(&self) {
+    let pos = (self.row_position * BUFFER_WIDTH + self.column_position) as u16;
+    Writer::write_cursor_location(pos);
+  }
+
+  #[cfg(not(test))]
+  fn write_cursor_location(pos: u16) {
+    unsafe {
+      outb(CRTC_ADDRESS, 0x0F);
+      outb(CRTC_DATA, (pos & 0xff) as u8);
+      outb(CRTC_ADDRESS, 0x0E);
+      outb(CRTC_DATA, (pos >> 8) as u8);
+    }
+  }
+
+  #[cfg(not(test))]
+  fn set_cursor_shape(start_scanline: u8, end_scanline: u8) {
+    unsafe {
+      outb(CRTC_ADDRESS, 0x0A);
+      outb(CRTC_DATA, (inb(CRTC_DATA) & 0xC0) | start_scanline);
+      outb(CRTC_ADDRESS, 0x0B);
+      outb(CRTC_DATA, (inb(CRTC_DATA) & 0xE0) | end_scanline);
+    }
+  }
+
+  #[cfg(not(test))]
+  fn hide_cursor() {
+    unsafe {
+      outb(CRTC_ADDRESS, 0x0A);
+      outb(CRTC_DATA, 0x20);
+    }
   }
 
+  // Under `cargo test` the kernel runs as a hosted binary where the CRTC ports
+  // are not available, so the hardware pokes become no-ops.
+  #[cfg(test)]
+  fn write_cursor_location(_pos: u16) {}
+
+  #[cfg(test)]
+  fn set_cursor_shape(_start_scanline: u8, _end_scanline: u8) {}
+
+  #[cfg(test)]
+  fn hide_cursor() {}
+
   fn new_line(&mut self) {
     if self.row_position == BUFFER_HEIGHT - 1 {
       for row in 1..BUFFER_HEIGHT {
@@ -133,6 +347,7 @@ impl Writer {
     }
 
     self.column_position = 0;
+    self.update_cursor();
   }
 
   fn clear_row(&mut self, row: usize) {
@@ -159,6 +374,11 @@ macro_rules! clear_screen {
   () => ($crate::vga_buffer::_clear_screen());
 }
 
+#[macro_export]
+macro_rules! move_cursor {
+  ($row:expr, $col:expr) => ($crate::vga_buffer::_move_cursor($row, $col));
+}
+
 /// Like the `print!` macro in the standard library, but prints to the VGA text buffer.
 #[macro_export]
 macro_rules! print {
@@ -184,6 +404,11 @@ macro_rules! debug {
 pub fn _print(args: fmt::Arguments) {
   use core::fmt::Write;
   WRITER.lock().write_fmt(args).unwrap();
+  // Mirror to the serial port only after releasing the VGA lock so the two
+  // locks are never held at once.
+  if crate::serial::mirror_enabled() {
+    crate::serial::SERIAL1.lock().write_fmt(args).unwrap();
+  }
 }
 
 #[doc(hidden)]
@@ -191,6 +416,11 @@ pub fn _clear_screen() {
   WRITER.lock().clear_screen();
 }
 
+#[doc(hidden)]
+pub fn _move_cursor(row: usize, col: usize) {
+  WRITER.lock().set_position(row, col);
+}
+
 #[doc(hidden)]
 pub fn _debug(level: DebugLevel, args: fmt::Arguments) {
   use core::fmt::Write;
@@ -200,6 +430,23 @@ pub fn _debug(level: DebugLevel, args: fmt::Arguments) {
   };
 
   WRITER.lock().with_color(color, &|writer| writer.write_fmt(args).unwrap());
+  // Serial output carries no color attribute; mirror the plain message once the
+  // VGA lock has been dropped to keep the lock order consistent.
+  if crate::serial::mirror_enabled() {
+    crate::serial::SERIAL1.lock().write_fmt(args).unwrap();
+  }
+}
+
+/// Forwards an already-formatted log record to the VGA buffer in `foreground`
+/// on the default background, mirroring it to the serial port like `_print`.
+#[doc(hidden)]
+pub fn _log(foreground: Color, args: fmt::Arguments) {
+  use core::fmt::Write;
+  let color = ColorCode::new(foreground, Color::Black);
+  WRITER.lock().with_color(color, &|writer| writer.write_fmt(args).unwrap());
+  if crate::serial::mirror_enabled() {
+    crate::serial::SERIAL1.lock().write_fmt(args).unwrap();
+  }
 }
 
 #[cfg(test)]
@@ -214,6 +461,9 @@ mod test {
       column_position: 0,
       row_position: 0,
       color_code: ColorCode::new(Color::Blue, Color::Magenta),
+      parser_state: ParserState::Ground,
+      params: [0; 8],
+      param_count: 0,
       buffer: Box::leak(Box::new(buffer)),
     }
   }
@@ -277,6 +527,67 @@ mod test {
     assert_eq!(writer.buffer.chars[0][1].read().color_code, original_color);
   }
 
+  #[test]
+  fn sgr_sets_color_without_emitting_filler() {
+    let mut writer = construct_writer();
+    // Green foreground, then a character, then reset.
+    writer.write_string("\x1b[32mX\x1b[0mY");
+
+    // The escape bytes must not have been rendered as filler cells.
+    assert_eq!(writer.column_position, 2);
+    assert_eq!(writer.buffer.chars[0][0].read().ascii_character, b'X');
+    assert_eq!(
+      writer.buffer.chars[0][0].read().color_code,
+      ColorCode::new(Color::Green, Color::Magenta)
+    );
+    assert_eq!(writer.buffer.chars[0][1].read().ascii_character, b'Y');
+    // Reset restores the default Yellow on Black.
+    assert_eq!(
+      writer.buffer.chars[0][1].read().color_code,
+      ColorCode::new(Color::Yellow, Color::Black)
+    );
+  }
+
+  #[test]
+  fn sgr_split_across_calls() {
+    let mut writer = construct_writer();
+    // A sequence spread over several writes must still be decoded.
+    writer.write_string("\x1b[4");
+    writer.write_string("1m");
+    writer.write_byte(b'Z');
+
+    assert_eq!(writer.column_position, 1);
+    assert_eq!(writer.buffer.chars[0][0].read().ascii_character, b'Z');
+    assert_eq!(
+      writer.buffer.chars[0][0].read().color_code,
+      ColorCode::new(Color::Blue, Color::Blue)
+    );
+  }
+
+  #[test]
+  fn malformed_sequence_drops_to_ground() {
+    let mut writer = construct_writer();
+    // `ESC X` is not a CSI introducer; the `X` should render normally.
+    writer.write_string("\x1bXAB");
+
+    assert_eq!(writer.buffer.chars[0][0].read().ascii_character, b'X');
+    assert_eq!(writer.buffer.chars[0][1].read().ascii_character, b'A');
+    assert_eq!(writer.buffer.chars[0][2].read().ascii_character, b'B');
+  }
+
+  #[test]
+  fn set_position_moves_software_cursor() {
+    let mut writer = construct_writer();
+    writer.set_position(3, 7);
+
+    assert_eq!(writer.row_position, 3);
+    assert_eq!(writer.column_position, 7);
+
+    writer.write_byte(b'X');
+    assert_eq!(writer.buffer.chars[3][7].read().ascii_character, b'X');
+    assert_eq!(writer.column_position, 8);
+  }
+
   #[test]
   fn write_formatted() {
     use core::fmt::Write;